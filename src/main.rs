@@ -1,16 +1,25 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     error::Error,
-    fs::File,
-    io::Read,
-    os::unix::process::{CommandExt, ExitStatusExt},
-    process::{Child, Command, Stdio},
-    sync::{atomic::AtomicBool, Arc, LazyLock, Mutex},
-    thread::sleep,
-    time::Duration,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    os::unix::{
+        net::{UnixListener, UnixStream},
+        process::{CommandExt, ExitStatusExt},
+    },
+    path::{Path, PathBuf},
+    process::{Child, ChildStdout, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, LazyLock, Mutex,
+    },
+    thread::{self, sleep},
+    time::{Duration, Instant},
 };
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use mlua::{Function, Lua, LuaSerdeExt, Table};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Parser)]
@@ -18,120 +27,808 @@ use serde::{Deserialize, Serialize};
 struct Args {
     #[arg(short)]
     config: Option<String>,
+    /// Directory of `conf.d`-style config fragments to merge in.
+    #[arg(long)]
+    config_dir: Option<String>,
+    #[command(subcommand)]
+    command: Option<ClientCommand>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+/// Subcommands that talk to a running supervisor over its control socket.
+#[derive(Debug, Subcommand)]
+enum ClientCommand {
+    Status,
+    Restart { name: String },
+    Stop { name: String },
+    Start { name: String },
+    Reload,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 struct App {
     name: Option<String>,
     path: String,
     args: Option<Vec<String>>,
     env: Option<HashMap<String, String>>,
-    restart: Option<bool>,
+    restart: Option<RestartPolicy>,
+    max_restarts: Option<u32>,
+    backoff_initial_ms: Option<u64>,
+    backoff_max_ms: Option<u64>,
+    backoff_factor: Option<f64>,
     stdout: Option<String>,
+    ready_when: Option<String>,
+    ready_timeout: Option<u64>,
+    depends_on: Option<Vec<String>>,
+    log: Option<LogConfig>,
+}
+
+impl App {
+    fn policy(&self) -> RestartPolicy {
+        self.restart.unwrap_or_default()
+    }
+
+    fn backoff_initial(&self) -> Duration {
+        Duration::from_millis(self.backoff_initial_ms.unwrap_or(DEFAULT_BACKOFF_INITIAL_MS))
+    }
+
+    fn backoff_max(&self) -> Duration {
+        Duration::from_millis(self.backoff_max_ms.unwrap_or(DEFAULT_BACKOFF_MAX_MS))
+    }
+
+    fn backoff_factor(&self) -> f64 {
+        self.backoff_factor.unwrap_or(DEFAULT_BACKOFF_FACTOR)
+    }
+}
+
+/// What to do when a supervised app exits. Mirrors the familiar init-system
+/// vocabulary. The default (`on-failure`) preserves the original behavior of
+/// restarting only on a nonzero exit or a signal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RestartPolicy {
+    No,
+    #[default]
+    OnFailure,
+    Always,
+}
+
+impl RestartPolicy {
+    /// Whether an exit with the given code/signal warrants a restart.
+    fn should_restart(self, code: Option<i32>, _signal: Option<i32>) -> bool {
+        match self {
+            RestartPolicy::No => false,
+            RestartPolicy::OnFailure => code != Some(0),
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
+/// Per-app crash-loop state driving the exponential backoff in `behold`.
+struct Backoff {
+    /// Consecutive failures since the app last stayed up past the window.
+    count: u32,
+    /// Delay applied to the next restart; grows by `backoff_factor`.
+    current_delay: Duration,
+    /// When the next scheduled restart is due, if one is pending.
+    next_spawn: Option<Instant>,
+    /// When the current child was last spawned, to detect a stable run.
+    last_spawn: Option<Instant>,
+    /// Set once `max_restarts` is exceeded; supervision of this app stops.
+    failed: bool,
+}
+
+/// Rotation policy for an app's `stdout` file, declared as a `[app.log]`
+/// sub-table. Rotation only applies when the app also sets `stdout`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct LogConfig {
+    /// Rotate once the active file grows past this many bytes (0 disables).
+    max_size_bytes: u64,
+    /// How many rotated files to keep before the oldest is discarded.
+    keep: usize,
+    #[serde(default)]
+    compress: Compress,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Compress {
+    #[default]
+    None,
+    Gzip,
+    Bzip2,
+}
+
+impl Compress {
+    /// File-name suffix for a rotated file compressed with this codec.
+    fn suffix(self) -> &'static str {
+        match self {
+            Compress::None => "",
+            Compress::Gzip => ".gz",
+            Compress::Bzip2 => ".bz2",
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 struct Config {
     interval: Option<u64>,
+    control_socket: Option<String>,
+    /// Optional Lua script providing `build`/`on_start`/`on_exit` hooks.
+    script: Option<String>,
     #[serde(rename = "app")]
     apps: Vec<App>,
 }
 
 const DEFAULT_CONFIG_PATH: &str = ".spawner.toml";
+const DEFAULT_CONTROL_SOCKET: &str = ".spawner.sock";
+const DEFAULT_READY_TIMEOUT: u64 = 30;
+const DEFAULT_BACKOFF_INITIAL_MS: u64 = 500;
+const DEFAULT_BACKOFF_MAX_MS: u64 = 30_000;
+const DEFAULT_BACKOFF_FACTOR: f64 = 2.0;
 static HANDBRAKE: LazyLock<Arc<AtomicBool>> = LazyLock::new(|| Arc::new(AtomicBool::new(false)));
 
-struct Cmd<'a> {
+struct Cmd {
     command: Arc<Mutex<Command>>,
     child: Arc<Mutex<Option<Child>>>,
-    app: &'a App,
+    ready: Arc<AtomicBool>,
+    ready_regex: Option<Regex>,
+    /// Number of restarts: automatic ones from `behold` plus `restart` control
+    /// commands. A `start` control command after a `stop` is not counted.
+    restarts: Arc<AtomicU32>,
+    /// Set by a `stop` control command so `behold` stops supervising the app.
+    stopped: Arc<AtomicBool>,
+    /// Crash-loop / backoff bookkeeping for the restart policy.
+    backoff: Arc<Mutex<Backoff>>,
+    /// Lua lifecycle hooks, shared with every app supervised in this run.
+    hooks: Option<SharedHooks>,
+    app: Arc<App>,
 }
 
-impl<'a> Cmd<'a> {
-    fn new(command: Command, child: Child, app: &'a App) -> Self {
+impl Cmd {
+    fn new(command: Command, child: Child, app: Arc<App>) -> Self {
+        let backoff = Backoff {
+            count: 0,
+            current_delay: app.backoff_initial(),
+            next_spawn: None,
+            last_spawn: Some(Instant::now()),
+            failed: false,
+        };
         Self {
             command: Arc::new(Mutex::new(command)),
             child: Arc::new(Mutex::new(Some(child))),
+            ready: Arc::new(AtomicBool::new(false)),
+            ready_regex: None,
+            restarts: Arc::new(AtomicU32::new(0)),
+            stopped: Arc::new(AtomicBool::new(false)),
+            backoff: Arc::new(Mutex::new(backoff)),
+            hooks: None,
             app,
         }
     }
+
+    /// Clear crash-loop state so a manually (re)started app gets a clean slate.
+    fn reset_backoff(&self) {
+        let mut backoff = self.backoff.lock().unwrap();
+        backoff.count = 0;
+        backoff.current_delay = self.app.backoff_initial();
+        backoff.next_spawn = None;
+        backoff.last_spawn = Some(Instant::now());
+        backoff.failed = false;
+    }
+}
+
+/// Lua runtime shared across worker threads; the `Mutex` serializes access
+/// since a single `Lua` state is not safe to call from several threads at once.
+type SharedHooks = Arc<Mutex<Hooks>>;
+
+/// The embedded Lua runtime with the user script loaded once. Exposes the
+/// `build`/`on_start`/`on_exit` lifecycle callbacks when the script defines
+/// them; a missing callback is simply a no-op.
+struct Hooks {
+    lua: Lua,
+}
+
+/// Adjustments a `build(app)` hook may return to shape the spawned command.
+#[derive(Default)]
+struct BuildResult {
+    program: Option<String>,
+    args: Vec<String>,
+    env: HashMap<String, String>,
+}
+
+impl Hooks {
+    fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let lua = Lua::new();
+        let src = std::fs::read_to_string(path)?;
+        lua.load(&src).set_name(path).exec()?;
+        Ok(Self { lua })
+    }
+
+    fn hook(&self, name: &str) -> mlua::Result<Option<Function>> {
+        self.lua.globals().get(name)
+    }
+
+    /// Let the script compute the final program, extra args and env for `app`.
+    fn build(&self, app: &App) -> mlua::Result<BuildResult> {
+        let Some(func) = self.hook("build")? else {
+            return Ok(BuildResult::default());
+        };
+        let app_val = self.lua.to_value(app)?;
+        let mut result = BuildResult::default();
+        if let Some(table) = func.call::<Option<Table>>(app_val)? {
+            result.program = table.get("program")?;
+            result.args = table.get::<Option<Vec<String>>>("args")?.unwrap_or_default();
+            result.env = table.get::<Option<HashMap<String, String>>>("env")?.unwrap_or_default();
+        }
+        Ok(result)
+    }
+
+    fn on_start(&self, app: &App, pid: u32) {
+        let result = (|| -> mlua::Result<()> {
+            if let Some(func) = self.hook("on_start")? {
+                func.call::<()>((self.lua.to_value(app)?, pid))?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            eprintln!("on_start hook failed for {}: {e}", app.path);
+        }
+    }
+
+    fn on_exit(&self, app: &App, code: Option<i32>, signal: Option<i32>) {
+        let result = (|| -> mlua::Result<()> {
+            if let Some(func) = self.hook("on_exit")? {
+                func.call::<()>((self.lua.to_value(app)?, code, signal))?;
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            eprintln!("on_exit hook failed for {}: {e}", app.path);
+        }
+    }
+}
+
+/// A control command received over the socket as newline-delimited JSON.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase")]
+enum ControlCommand {
+    Status,
+    Restart { name: String },
+    Stop { name: String },
+    Start { name: String },
+    Reload,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AppStatus {
+    name: Option<String>,
+    pid: Option<u32>,
+    running: bool,
+    ready: bool,
+    restarts: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ControlResponse {
+    Status { apps: Vec<AppStatus> },
+    Ack { ok: bool, message: String },
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
-    println!("parsed args: {:#?}", args);
 
-    let config = parse(&args)?;
+    let config_files = discover_config_files(&args);
+
+    // Client subcommands just relay a command to a running supervisor; they
+    // only need the socket path, which resolves even with no config file.
+    if let Some(command) = &args.command {
+        let socket = control_socket_path(load_configs(&config_files).ok().as_ref());
+        return run_client(command, &socket);
+    }
+
+    let config = load_configs(&config_files)?;
+
+    println!("parsed args: {:#?}", args);
     println!("read conf:\n{}", toml::to_string_pretty(&config)?);
 
     setup().unwrap();
 
-    let cmds = start(&config)?;
-    println!("spawned {} apps", cmds.len());
+    // Load the optional Lua script once; pure-TOML configs keep working as-is.
+    let hooks = config
+        .script
+        .as_deref()
+        .map(Hooks::load)
+        .transpose()?
+        .map(|h| Arc::new(Mutex::new(h)));
+
+    let cmds = Arc::new(Mutex::new(start(&config, hooks.clone())?));
+    println!("spawned {} apps", cmds.lock().unwrap().len());
+
+    let socket = control_socket_path(Some(&config));
+    {
+        let path = socket.clone();
+        let cmds = cmds.clone();
+        let config_files = config_files.clone();
+        thread::spawn(move || serve_control(path, cmds, config_files, hooks));
+    }
 
     behold(&config, &cmds)?;
 
+    let _ = std::fs::remove_file(&socket);
+
     Ok(())
 }
 
 fn setup() -> Result<(), Box<dyn Error>> {
     ctrlc::set_handler(move || {
-        HANDBRAKE.store(true, std::sync::atomic::Ordering::SeqCst);
+        HANDBRAKE.store(true, Ordering::SeqCst);
     })
     .unwrap();
 
     Ok(())
 }
 
-fn parse(Args { config, .. }: &Args) -> Result<Config, Box<dyn Error>> {
-    let mut input = File::open(config.as_ref().map_or(DEFAULT_CONFIG_PATH, |x| x.as_str()))?;
+/// The ordered list of config files to consider. An explicit `-c` wins over
+/// discovery; otherwise the XDG config dir is tried before `./.spawner.toml`.
+/// A `--config-dir` of `conf.d`-style fragments is appended last.
+fn discover_config_files(args: &Args) -> Vec<PathBuf> {
+    let mut files = vec![];
+
+    if let Some(config) = &args.config {
+        files.push(PathBuf::from(config));
+    } else {
+        if let Some(dir) = dirs::config_dir() {
+            files.push(dir.join("spawner").join("config.toml"));
+        }
+        files.push(PathBuf::from(DEFAULT_CONFIG_PATH));
+    }
+
+    if let Some(dir) = &args.config_dir {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            let mut fragments: Vec<PathBuf> = entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+                .collect();
+            fragments.sort();
+            files.extend(fragments);
+        }
+    }
+
+    files.into_iter().filter(|p| p.exists()).collect()
+}
+
+/// Merge an ordered list of config files into one `Config`. Top-level scalars
+/// follow last-writer-wins; `apps` are concatenated and duplicate `name`s are
+/// an error. The files actually loaded are printed.
+fn load_configs(files: &[PathBuf]) -> Result<Config, Box<dyn Error>> {
+    if files.is_empty() {
+        return Err("no config file found".into());
+    }
+
+    let mut merged = Config::default();
+    let mut seen = HashSet::new();
+    for path in files {
+        println!("loading config {}", path.display());
+        let text = std::fs::read_to_string(path)?;
+        let cfg: Config = toml::from_str(&text)?;
+
+        merged.interval = cfg.interval.or(merged.interval);
+        merged.control_socket = cfg.control_socket.or(merged.control_socket);
+        merged.script = cfg.script.or(merged.script);
+
+        for app in cfg.apps {
+            if let Some(name) = &app.name {
+                if !seen.insert(name.clone()) {
+                    return Err(
+                        format!("duplicate app name {name:?} across config files").into(),
+                    );
+                }
+            }
+            merged.apps.push(app);
+        }
+    }
+
+    Ok(merged)
+}
 
-    let mut config = String::new();
-    input.read_to_string(&mut config)?;
-    let x: Config = toml::from_str(&config)?;
+/// Resolve the control-socket path: the configured value when set, otherwise a
+/// default placed under the user's config dir (falling back to the cwd when no
+/// config dir is available). Takes an optional `Config` so client subcommands
+/// can resolve the socket without a config file present.
+fn control_socket_path(config: Option<&Config>) -> PathBuf {
+    if let Some(socket) = config.and_then(|c| c.control_socket.clone()) {
+        return PathBuf::from(socket);
+    }
+    if let Some(dir) = dirs::config_dir() {
+        return dir.join("spawner").join(DEFAULT_CONTROL_SOCKET);
+    }
+    PathBuf::from(DEFAULT_CONTROL_SOCKET)
+}
 
-    Ok(x)
+/// A rotating stdout sink: lines are appended to `base`, and once it grows
+/// past `max_size` the active file is rolled to `base.1`, older files shift up
+/// (`base.1`→`base.2`…), anything beyond `keep` is dropped, and — when a codec
+/// is configured — the freshly rotated file is compressed before the next
+/// rotation can run, via a temp-file-and-rename so the chain stays consistent.
+struct RotatingSink {
+    base: PathBuf,
+    max_size: u64,
+    keep: usize,
+    compress: Compress,
+    file: File,
+    size: u64,
 }
 
-fn start(x: &Config) -> Result<Vec<Cmd>, Box<dyn Error>> {
-    let mut cmds = vec![];
+impl RotatingSink {
+    fn new(base: PathBuf, cfg: &LogConfig) -> io::Result<Self> {
+        // Append rather than truncate so a restart keeps the existing log; the
+        // size is seeded from the file on disk so rotation still fires on the
+        // cumulative size across restarts.
+        let file = OpenOptions::new().create(true).append(true).open(&base)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            base,
+            max_size: cfg.max_size_bytes,
+            keep: cfg.keep,
+            compress: cfg.compress,
+            file,
+            size,
+        })
+    }
+
+    /// Path of the `n`th rotated file, carrying the compression suffix.
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut s = self.base.clone().into_os_string();
+        s.push(format!(".{n}{}", self.compress.suffix()));
+        PathBuf::from(s)
+    }
 
-    for app in x.apps.iter() {
-        let mut command = Command::new(&app.path);
-        if let Some(out) = &app.stdout {
-            let f = File::create(out)?;
-            command.stdout(Stdio::from(f));
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        // With no rotated files to keep, just start a fresh active file and
+        // discard the old contents outright — no `.1` is retained.
+        if self.keep == 0 {
+            self.file = File::create(&self.base)?;
+            self.size = 0;
+            return Ok(());
         }
 
-        if let Some(args) = &app.args {
-            command.args(args);
+        // Shift existing rotated files up and drop anything past `keep`.
+        let _ = std::fs::remove_file(self.rotated(self.keep));
+        for n in (1..self.keep).rev() {
+            let (src, dst) = (self.rotated(n), self.rotated(n + 1));
+            if src.exists() {
+                std::fs::rename(src, dst)?;
+            }
         }
 
-        if let Some(env) = &app.env {
-            command.envs(
-                env.iter()
-                    .map(|(k, v)| (k, shellexpand::env(v).unwrap().to_string())),
-            );
+        // Roll the active file aside atomically, then reopen a fresh one. The
+        // rename is a single syscall so no buffered lines are lost.
+        let mut rolled = self.base.clone().into_os_string();
+        rolled.push(".1");
+        let rolled = PathBuf::from(rolled);
+        std::fs::rename(&self.base, &rolled)?;
+
+        self.file = File::create(&self.base)?;
+        self.size = 0;
+
+        // Compress synchronously: the rotated `.N` file is fully written (and
+        // its uncompressed intermediate removed) before `rotate` returns, so a
+        // rapid second rotation can't clobber an in-flight compression or roll
+        // a half-written archive up the chain.
+        if self.compress != Compress::None {
+            compress_file(&rolled, &self.rotated(1), self.compress)?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for RotatingSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.max_size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Compress `src` into `dst`, removing `src` when done. The archive is written
+/// to a temporary sibling and atomically renamed into place, so a reader never
+/// observes a half-written `dst` and the rotation chain stays consistent.
+fn compress_file(src: &Path, dst: &Path, compress: Compress) -> io::Result<()> {
+    let mut tmp = dst.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut input = File::open(src)?;
+    let output = File::create(&tmp)?;
+    match compress {
+        Compress::Gzip => {
+            let mut enc = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+            io::copy(&mut input, &mut enc)?;
+            enc.finish()?;
+        }
+        Compress::Bzip2 => {
+            let mut enc = bzip2::write::BzEncoder::new(output, bzip2::Compression::default());
+            io::copy(&mut input, &mut enc)?;
+            enc.finish()?;
+        }
+        Compress::None => {}
+    }
+    std::fs::rename(&tmp, dst)?;
+    std::fs::remove_file(src)
+}
+
+/// Whether an app's stdout must be piped through a reader thread, i.e. it needs
+/// readiness scanning or log rotation rather than a plain `Stdio` file.
+fn is_piped(app: &App) -> bool {
+    app.ready_when.is_some() || app.log.is_some()
+}
+
+/// Build the stdout sink for a piped app: a rotating sink when `[app.log]` is
+/// set, otherwise a plain file when `stdout` is set, otherwise nothing.
+fn log_sink(app: &App) -> io::Result<Option<Box<dyn Write + Send>>> {
+    match (&app.log, &app.stdout) {
+        (Some(cfg), Some(path)) => Ok(Some(Box::new(RotatingSink::new(PathBuf::from(path), cfg)?))),
+        (Some(_), None) => {
+            eprintln!("{}: [log] set without stdout; logs are not captured", app.path);
+            Ok(None)
+        }
+        (None, Some(path)) => Ok(Some(Box::new(File::create(path)?))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Drain a piped child stdout line-by-line, writing each line to `sink` (a
+/// rotating or plain file) and, when `regex` is set, flipping `ready` on the
+/// first matching line. The thread ends on EOF, i.e. when the child is killed
+/// by HANDBRAKE.
+fn pipe_stdout(
+    stdout: ChildStdout,
+    regex: Option<Regex>,
+    ready: Arc<AtomicBool>,
+    mut sink: Option<Box<dyn Write + Send>>,
+) {
+    thread::spawn(move || {
+        let reader = BufReader::new(stdout);
+        for line in reader.lines() {
+            let Ok(line) = line else { break };
+
+            if let Some(sink) = sink.as_mut() {
+                let _ = writeln!(sink, "{line}");
+            }
+
+            if let Some(regex) = &regex {
+                if regex.is_match(&line) {
+                    ready.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+    });
+}
+
+/// Spawn a single app and, when readiness gating is configured, block until
+/// it reports ready (or its timeout elapses). Dependency ordering in `start`
+/// guarantees this has returned before any dependent app is spawned.
+fn spawn_app(app: Arc<App>, hooks: Option<SharedHooks>) -> Result<Cmd, Box<dyn Error>> {
+    // A `build(app)` hook may compute the final program and extra args/env at
+    // spawn time; without a script this is an empty, inert adjustment.
+    let build = match &hooks {
+        Some(hooks) => hooks.lock().unwrap().build(&app)?,
+        None => BuildResult::default(),
+    };
+
+    let program = build.program.as_deref().unwrap_or(&app.path);
+    let mut command = Command::new(program);
+
+    // When readiness gating is requested we must own the child's stdout to
+    // scan it, so the file (if any) is written from the reader thread as a
+    // tee rather than handed straight to `Stdio`.
+    let ready_regex = app.ready_when.as_deref().map(Regex::new).transpose()?;
+    if is_piped(&app) {
+        command.stdout(Stdio::piped());
+    } else if let Some(out) = &app.stdout {
+        let f = File::create(out)?;
+        command.stdout(Stdio::from(f));
+    }
+
+    if let Some(args) = &app.args {
+        command.args(args);
+    }
+    command.args(&build.args);
+
+    if let Some(env) = &app.env {
+        command.envs(
+            env.iter()
+                .map(|(k, v)| (k, shellexpand::env(v).unwrap().to_string())),
+        );
+    }
+    command.envs(&build.env);
+
+    if let Some(name) = &app.name {
+        command.arg0(format!("[spawner: {name}] -> {}", &app.path));
+    }
+
+    let handle = command.spawn()?;
+    let pid = handle.id();
+    println!("starting {} with PID: {pid}", app.path);
+
+    if let Some(hooks) = &hooks {
+        hooks.lock().unwrap().on_start(&app, pid);
+    }
+
+    let mut cmd = Cmd::new(command, handle, app.clone());
+    cmd.hooks = hooks;
+
+    if is_piped(&app) {
+        let stdout = cmd
+            .child
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(|c| c.stdout.take());
+        if let Some(stdout) = stdout {
+            let sink = log_sink(&app)?;
+            pipe_stdout(stdout, ready_regex.clone(), cmd.ready.clone(), sink);
+        }
+        cmd.ready_regex = ready_regex;
+    }
+
+    if cmd.ready_regex.is_some() {
+        let timeout = Duration::from_secs(app.ready_timeout.unwrap_or(DEFAULT_READY_TIMEOUT));
+        let deadline = Instant::now() + timeout;
+        while !cmd.ready.load(Ordering::SeqCst) {
+            // Fail fast if the child died before ever signalling readiness,
+            // rather than blocking the whole timeout and stalling dependents.
+            if let Ok(Some(_)) = cmd.child.lock().unwrap().as_mut().map_or(Ok(None), |c| c.try_wait())
+            {
+                eprintln!("{} failed to start: exited before ready", app.path);
+                break;
+            }
+            if Instant::now() >= deadline {
+                eprintln!("{} failed to start: readiness timed out", app.path);
+                if let Some(child) = cmd.child.lock().unwrap().as_mut() {
+                    let _ = child.kill();
+                }
+                break;
+            }
+            sleep(Duration::from_millis(50));
+        }
+    } else {
+        cmd.ready.store(true, Ordering::SeqCst);
+    }
+
+    Ok(cmd)
+}
+
+/// Re-spawn an existing `Cmd` in place, re-arming its readiness watcher against
+/// the fresh child. Used by both the supervision loop and the control socket.
+fn respawn(cmd: &Cmd) -> Result<(), Box<dyn Error>> {
+    let mut new = cmd.command.lock().unwrap().spawn()?;
+
+    cmd.ready.store(false, Ordering::SeqCst);
+    if is_piped(&cmd.app) {
+        if let Some(stdout) = new.stdout.take() {
+            let sink = log_sink(&cmd.app)?;
+            pipe_stdout(stdout, cmd.ready_regex.clone(), cmd.ready.clone(), sink);
+        }
+        // A log-only app (no readiness regex) is ready as soon as it is up.
+        if cmd.ready_regex.is_none() {
+            cmd.ready.store(true, Ordering::SeqCst);
         }
+    } else {
+        cmd.ready.store(true, Ordering::SeqCst);
+    }
+
+    let pid = new.id();
+    cmd.child.lock().unwrap().replace(new);
+
+    if let Some(hooks) = &cmd.hooks {
+        hooks.lock().unwrap().on_start(&cmd.app, pid);
+    }
+    Ok(())
+}
 
-        if let Some(name) = &app.name {
-            command.arg0(format!("[spawner: {name}] -> {}", &app.path));
+/// Resolve `depends_on` edges into startup levels: each level holds the
+/// indices of apps whose dependencies have all been started in earlier levels,
+/// so a level can be launched in parallel. Errors if the graph has a cycle.
+fn startup_levels(apps: &[App]) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
+    let index_by_name: HashMap<&str, usize> = apps
+        .iter()
+        .enumerate()
+        .filter_map(|(i, a)| a.name.as_deref().map(|n| (n, i)))
+        .collect();
+
+    let mut indegree = vec![0usize; apps.len()];
+    let mut dependents: Vec<Vec<usize>> = vec![vec![]; apps.len()];
+    for (i, app) in apps.iter().enumerate() {
+        for dep in app.depends_on.iter().flatten() {
+            let &j = index_by_name
+                .get(dep.as_str())
+                .ok_or_else(|| format!("app {:?} depends on unknown app {:?}", app.path, dep))?;
+            indegree[i] += 1;
+            dependents[j].push(i);
+        }
+    }
+
+    let mut levels = vec![];
+    let mut remaining = apps.len();
+    let mut ready: Vec<usize> = (0..apps.len()).filter(|&i| indegree[i] == 0).collect();
+    while !ready.is_empty() {
+        remaining -= ready.len();
+        let mut next = vec![];
+        for &i in &ready {
+            for &d in &dependents[i] {
+                indegree[d] -= 1;
+                if indegree[d] == 0 {
+                    next.push(d);
+                }
+            }
         }
+        levels.push(ready);
+        ready = next;
+    }
+
+    if remaining > 0 {
+        let cycle: Vec<&str> = indegree
+            .iter()
+            .enumerate()
+            .filter(|(_, &d)| d > 0)
+            .map(|(i, _)| apps[i].name.as_deref().unwrap_or(apps[i].path.as_str()))
+            .collect();
+        return Err(format!("dependency cycle among: {}", cycle.join(" -> ")).into());
+    }
+
+    Ok(levels)
+}
 
-        let handle = command.spawn()?;
-        println!("starting {} with PID: {}", app.path, handle.id());
+fn start(x: &Config, hooks: Option<SharedHooks>) -> Result<Vec<Cmd>, Box<dyn Error>> {
+    let levels = startup_levels(&x.apps)?;
+    let apps: Vec<Arc<App>> = x.apps.iter().cloned().map(Arc::new).collect();
+    let mut cmds: Vec<Option<Cmd>> = (0..apps.len()).map(|_| None).collect();
 
-        cmds.push(Cmd::new(command, handle, app));
+    // Spawn level by level: apps within a level are independent and launched on
+    // their own worker threads, while the level boundary blocks until each app's
+    // readiness (established in `spawn_app`) is satisfied before dependents run.
+    for level in levels {
+        let results = thread::scope(|scope| {
+            let handles: Vec<_> = level
+                .iter()
+                .map(|&i| {
+                    let app = apps[i].clone();
+                    let hooks = hooks.clone();
+                    scope.spawn(move || (i, spawn_app(app, hooks).map_err(|e| e.to_string())))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        for (i, result) in results {
+            cmds[i] = Some(result?);
+        }
     }
 
-    Ok(cmds)
+    Ok(cmds.into_iter().map(|c| c.unwrap()).collect())
 }
 
-fn behold(config: &Config, cmds: &Vec<Cmd>) -> Result<(), Box<dyn Error>> {
-    let interval = config.interval.map_or(5000, |x| x * 1000);
+fn behold(config: &Config, cmds: &Arc<Mutex<Vec<Cmd>>>) -> Result<(), Box<dyn Error>> {
+    let interval = Duration::from_millis(config.interval.map_or(5000, |x| x * 1000));
     loop {
-        if HANDBRAKE.load(std::sync::atomic::Ordering::SeqCst) {
+        if HANDBRAKE.load(Ordering::SeqCst) {
             println!("exit triggered from ctrlc");
-            for Cmd { child, .. } in cmds.iter() {
+            for Cmd { child, .. } in cmds.lock().unwrap().iter() {
                 let mut child = child.lock().unwrap();
                 if let Some(child) = child.as_mut() {
                     child.kill().unwrap();
@@ -141,69 +838,386 @@ fn behold(config: &Config, cmds: &Vec<Cmd>) -> Result<(), Box<dyn Error>> {
             break;
         }
 
-        sleep(Duration::from_millis(interval));
+        let now = Instant::now();
+        // The sleep is driven by the nearest pending restart deadline so
+        // backoff timing is honored rather than rounded up to `interval`.
+        let mut next_wake = interval;
 
-        for Cmd {
-            command,
-            child,
-            app,
-        } in cmds.iter()
-        {
-            let command = command.clone();
-            let child = child.clone();
-            let mut child = child.lock().unwrap();
-            let mut command = command.lock().unwrap();
-
-            let (running, restart) =
-                child
-                    .as_mut()
-                    .map_or((false, false), |x| match x.try_wait() {
-                        Ok(Some(x)) => match x.code() {
-                            Some(code) => match code {
-                                0 => {
-                                    println!("exited without error");
-                                    (false, false)
-                                }
-                                code => {
-                                    println!("exited with code: {code}");
-                                    (false, true)
-                                }
-                            },
-                            None => {
-                                println!(
-                                    "killed by signal: {}",
-                                    x.signal().map_or("unknown".to_string(), |s| s.to_string())
-                                );
-                                (false, true)
-                            }
-                        },
-                        Ok(None) => {
-                            // println!("still running");
-                            (true, false)
-                        }
-                        Err(e) => panic!("{}", e),
-                    });
-
-            if !running {
+        for cmd in cmds.lock().unwrap().iter() {
+            let mut child = cmd.child.lock().unwrap();
+
+            let exit = child.as_mut().and_then(|x| match x.try_wait() {
+                Ok(Some(x)) => match x.code() {
+                    Some(0) => {
+                        println!("exited without error");
+                        Some((Some(0), None))
+                    }
+                    Some(code) => {
+                        println!("exited with code: {code}");
+                        Some((Some(code), None))
+                    }
+                    None => {
+                        println!(
+                            "killed by signal: {}",
+                            x.signal().map_or("unknown".to_string(), |s| s.to_string())
+                        );
+                        Some((None, x.signal()))
+                    }
+                },
+                Ok(None) => None,
+                Err(e) => panic!("{}", e),
+            });
+
+            let running = child.is_some() && exit.is_none();
+            if exit.is_some() {
                 child.take();
+                cmd.ready.store(false, Ordering::SeqCst);
+            }
+            drop(child);
+
+            // Fire the Lua `on_exit` hook from the exit-handling arm above.
+            if let Some((code, signal)) = exit {
+                if let Some(hooks) = &cmd.hooks {
+                    hooks.lock().unwrap().on_exit(&cmd.app, code, signal);
+                }
+            }
+
+            let mut backoff = cmd.backoff.lock().unwrap();
+
+            // A run that has stayed up past the backoff window is considered
+            // healthy, so the delay and failure count are reset.
+            if running {
+                if let Some(started) = backoff.last_spawn {
+                    if backoff.count > 0 && now.duration_since(started) > cmd.app.backoff_max() {
+                        backoff.count = 0;
+                        backoff.current_delay = cmd.app.backoff_initial();
+                    }
+                }
+            }
+
+            // On an unexpected exit, schedule the next restart with backoff, or
+            // mark the app failed once it blows past `max_restarts`.
+            if let Some((code, signal)) = exit {
+                let supervised = !cmd.stopped.load(Ordering::SeqCst);
+                if supervised
+                    && !backoff.failed
+                    && cmd.app.policy().should_restart(code, signal)
+                {
+                    backoff.count += 1;
+                    if cmd.app.max_restarts.is_some_and(|max| backoff.count > max) {
+                        backoff.failed = true;
+                        eprintln!(
+                            "{} exceeded max_restarts; no longer restarting",
+                            cmd.app.path
+                        );
+                    } else {
+                        let delay = backoff.current_delay;
+                        backoff.next_spawn = Some(now + delay);
+                        let grown = (delay.as_millis() as f64 * cmd.app.backoff_factor()) as u64;
+                        backoff.current_delay =
+                            Duration::from_millis(grown).min(cmd.app.backoff_max());
+                        println!("scheduling restart of {} in {delay:?}", cmd.app.path);
+                    }
+                }
+            }
+
+            // Carry out a restart whose deadline has arrived.
+            if backoff.next_spawn.is_some_and(|deadline| now >= deadline) && !backoff.failed {
+                backoff.next_spawn = None;
+                backoff.last_spawn = Some(Instant::now());
+                drop(backoff);
+                respawn(cmd)?;
+                cmd.restarts.fetch_add(1, Ordering::SeqCst);
+                backoff = cmd.backoff.lock().unwrap();
             }
 
-            if restart && app.restart.unwrap_or(true) {
-                let new = command.spawn()?;
-                child.replace(new);
+            if let Some(deadline) = backoff.next_spawn {
+                next_wake = next_wake.min(deadline.saturating_duration_since(now));
             }
+            drop(backoff);
 
             println!(
                 "{} {}",
-                command.get_program().to_str().unwrap(),
-                if running {
-                    format!("running as PID: {}", child.as_ref().map_or(0, |x| x.id()))
+                cmd.command.lock().unwrap().get_program().to_str().unwrap(),
+                if cmd.child.lock().unwrap().is_some() {
+                    format!(
+                        "running as PID: {}",
+                        cmd.child.lock().unwrap().as_ref().map_or(0, |x| x.id())
+                    )
                 } else {
-                    format!("not running")
+                    "not running".to_string()
                 }
             );
         }
+
+        sleep(next_wake.max(Duration::from_millis(50)));
+    }
+
+    Ok(())
+}
+
+/// Listen on the control socket and dispatch newline-delimited JSON commands
+/// until HANDBRAKE is tripped. Mirrors the command channel a process manager
+/// exposes for out-of-band status and lifecycle control.
+fn serve_control(
+    path: PathBuf,
+    cmds: Arc<Mutex<Vec<Cmd>>>,
+    config_files: Vec<PathBuf>,
+    hooks: Option<SharedHooks>,
+) {
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = std::fs::remove_file(&path);
+    let listener = match UnixListener::bind(&path) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("could not bind control socket {}: {e}", path.display());
+            return;
+        }
+    };
+    listener.set_nonblocking(true).ok();
+    println!("control socket listening on {}", path.display());
+
+    for stream in listener.incoming() {
+        if HANDBRAKE.load(Ordering::SeqCst) {
+            break;
+        }
+        match stream {
+            Ok(stream) => {
+                // Serve each connection on its own thread so a client that
+                // holds the socket open can't block other operators' commands.
+                let cmds = cmds.clone();
+                let config_files = config_files.clone();
+                let hooks = hooks.clone();
+                thread::spawn(move || handle_control(stream, &cmds, &config_files, hooks));
+            }
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                sleep(Duration::from_millis(200));
+            }
+            Err(e) => eprintln!("control socket accept failed: {e}"),
+        }
     }
+}
+
+fn handle_control(
+    stream: UnixStream,
+    cmds: &Arc<Mutex<Vec<Cmd>>>,
+    config_files: &[PathBuf],
+    hooks: Option<SharedHooks>,
+) {
+    let mut writer = match stream.try_clone() {
+        Ok(w) => w,
+        Err(e) => {
+            eprintln!("control connection clone failed: {e}");
+            return;
+        }
+    };
+    let reader = BufReader::new(stream);
+    for line in reader.lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<ControlCommand>(&line) {
+            Ok(command) => dispatch(command, cmds, config_files, &hooks),
+            Err(e) => ControlResponse::Ack {
+                ok: false,
+                message: format!("invalid command: {e}"),
+            },
+        };
+
+        if let Ok(body) = serde_json::to_string(&response) {
+            let _ = writeln!(writer, "{body}");
+        }
+    }
+}
+
+fn dispatch(
+    command: ControlCommand,
+    cmds: &Arc<Mutex<Vec<Cmd>>>,
+    config_files: &[PathBuf],
+    hooks: &Option<SharedHooks>,
+) -> ControlResponse {
+    match command {
+        ControlCommand::Status => {
+            let cmds = cmds.lock().unwrap();
+            let apps = cmds
+                .iter()
+                .map(|cmd| {
+                    let child = cmd.child.lock().unwrap();
+                    AppStatus {
+                        name: cmd.app.name.clone(),
+                        pid: child.as_ref().map(|c| c.id()),
+                        running: child.is_some(),
+                        ready: cmd.ready.load(Ordering::SeqCst),
+                        restarts: cmd.restarts.load(Ordering::SeqCst),
+                    }
+                })
+                .collect();
+            ControlResponse::Status { apps }
+        }
+        ControlCommand::Restart { name } => with_app(cmds, &name, |cmd| {
+            // Reap the outgoing child so `respawn` doesn't leave a zombie.
+            if let Some(mut child) = cmd.child.lock().unwrap().take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            cmd.reset_backoff();
+            respawn(cmd).map_err(|e| e.to_string())?;
+            cmd.stopped.store(false, Ordering::SeqCst);
+            cmd.restarts.fetch_add(1, Ordering::SeqCst);
+            Ok(format!("restarted {name}"))
+        }),
+        ControlCommand::Stop { name } => with_app(cmds, &name, |cmd| {
+            cmd.stopped.store(true, Ordering::SeqCst);
+            if let Some(mut child) = cmd.child.lock().unwrap().take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            cmd.ready.store(false, Ordering::SeqCst);
+            Ok(format!("stopped {name}"))
+        }),
+        ControlCommand::Start { name } => with_app(cmds, &name, |cmd| {
+            if cmd.child.lock().unwrap().is_some() {
+                return Ok(format!("{name} already running"));
+            }
+            cmd.reset_backoff();
+            respawn(cmd).map_err(|e| e.to_string())?;
+            cmd.stopped.store(false, Ordering::SeqCst);
+            Ok(format!("started {name}"))
+        }),
+        ControlCommand::Reload => reload(cmds, config_files, hooks),
+    }
+}
+
+/// Run `f` against the app named `name`, turning its result into a response.
+fn with_app<F>(cmds: &Arc<Mutex<Vec<Cmd>>>, name: &str, f: F) -> ControlResponse
+where
+    F: FnOnce(&Cmd) -> Result<String, String>,
+{
+    let cmds = cmds.lock().unwrap();
+    let Some(cmd) = cmds
+        .iter()
+        .find(|c| c.app.name.as_deref() == Some(name))
+    else {
+        return ControlResponse::Ack {
+            ok: false,
+            message: format!("no app named {name:?}"),
+        };
+    };
+
+    match f(cmd) {
+        Ok(message) => ControlResponse::Ack { ok: true, message },
+        Err(message) => ControlResponse::Ack { ok: false, message },
+    }
+}
+
+/// Re-read the config files and reconcile them against the running set: newly
+/// declared apps are spawned (with the same Lua lifecycle hooks as start-time
+/// apps), removed ones are stopped and dropped. Apps are matched by `name`, so
+/// unnamed apps are left untouched.
+///
+/// Limitation: reloaded apps are spawned individually and do NOT participate in
+/// `depends_on` topological ordering — that graph is only resolved once, at
+/// start. A newly added app with dependencies is launched immediately rather
+/// than waiting for them.
+fn reload(
+    cmds: &Arc<Mutex<Vec<Cmd>>>,
+    config_files: &[PathBuf],
+    hooks: &Option<SharedHooks>,
+) -> ControlResponse {
+    let config = match load_configs(config_files) {
+        Ok(c) => c,
+        Err(e) => {
+            return ControlResponse::Ack {
+                ok: false,
+                message: format!("reload failed: {e}"),
+            }
+        }
+    };
+
+    // Under the lock, reap apps no longer in the config and snapshot which
+    // names are still running. The lock is then released before spawning so
+    // the readiness-gating in `spawn_app` can't stall `behold` or other
+    // control commands for the full `ready_timeout`.
+    let (current, removed) = {
+        let mut cmds = cmds.lock().unwrap();
+        let desired: HashSet<&str> =
+            config.apps.iter().filter_map(|a| a.name.as_deref()).collect();
+        let mut removed = 0;
+        cmds.retain(|cmd| {
+            let keep = cmd
+                .app
+                .name
+                .as_deref()
+                .map(|n| desired.contains(n))
+                .unwrap_or(true);
+            if !keep {
+                if let Some(mut child) = cmd.child.lock().unwrap().take() {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                removed += 1;
+            }
+            keep
+        });
+        let current: HashSet<String> = cmds.iter().filter_map(|c| c.app.name.clone()).collect();
+        (current, removed)
+    };
+
+    // Spawn apps that were newly declared without holding the lock, then
+    // re-acquire it to insert them into the supervised set.
+    let mut spawned = Vec::new();
+    let mut added = 0;
+    for app in &config.apps {
+        let Some(name) = app.name.as_deref() else {
+            continue;
+        };
+        if current.contains(name) {
+            continue;
+        }
+        match spawn_app(Arc::new(app.clone()), hooks.clone()) {
+            Ok(cmd) => {
+                spawned.push(cmd);
+                added += 1;
+            }
+            Err(e) => {
+                cmds.lock().unwrap().extend(spawned);
+                return ControlResponse::Ack {
+                    ok: false,
+                    message: format!("reload: failed to start {name:?}: {e}"),
+                };
+            }
+        }
+    }
+    cmds.lock().unwrap().extend(spawned);
+
+    ControlResponse::Ack {
+        ok: true,
+        message: format!("reloaded: {added} added, {removed} removed"),
+    }
+}
+
+/// Connect to a running supervisor's control socket, send one command, and
+/// print its JSON reply.
+fn run_client(command: &ClientCommand, path: &Path) -> Result<(), Box<dyn Error>> {
+    let control = match command {
+        ClientCommand::Status => ControlCommand::Status,
+        ClientCommand::Restart { name } => ControlCommand::Restart { name: name.clone() },
+        ClientCommand::Stop { name } => ControlCommand::Stop { name: name.clone() },
+        ClientCommand::Start { name } => ControlCommand::Start { name: name.clone() },
+        ClientCommand::Reload => ControlCommand::Reload,
+    };
+
+    let mut stream = UnixStream::connect(path)?;
+    writeln!(stream, "{}", serde_json::to_string(&control)?)?;
+
+    let mut reader = BufReader::new(stream);
+    let mut reply = String::new();
+    reader.read_line(&mut reply)?;
+    println!("{}", reply.trim_end());
 
     Ok(())
 }
@@ -220,7 +1234,7 @@ mod tests {
         name = "some"
         path = "/bin/sh"
         args = ["-c", "echo", "hola"]
-    
+
         [[app]]
         path = "/bin/sleep"
         env = { PATH = "b", c = "d"}
@@ -245,4 +1259,86 @@ mod tests {
         dbg!(x);
         println!("{}", toml::to_string_pretty(&apps).unwrap());
     }
+
+    fn named(name: &str, deps: &[&str]) -> App {
+        App {
+            name: Some(name.to_string()),
+            path: "/bin/true".to_string(),
+            depends_on: if deps.is_empty() {
+                None
+            } else {
+                Some(deps.iter().map(|d| d.to_string()).collect())
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn startup_levels_orders_by_dependency() {
+        // c depends on b, b depends on a: three sequential levels.
+        let apps = vec![named("c", &["b"]), named("a", &[]), named("b", &["a"])];
+        let levels = startup_levels(&apps).unwrap();
+        assert_eq!(levels, vec![vec![1], vec![2], vec![0]]);
+    }
+
+    #[test]
+    fn startup_levels_rejects_cycles() {
+        let apps = vec![named("a", &["b"]), named("b", &["a"])];
+        assert!(startup_levels(&apps).is_err());
+    }
+
+    #[test]
+    fn load_configs_merges_and_rejects_duplicates() {
+        let dir = std::env::temp_dir().join("spawner_load_configs_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.toml");
+        let b = dir.join("b.toml");
+        std::fs::write(&a, "interval = 2\n[[app]]\nname = \"one\"\npath = \"/bin/true\"\n").unwrap();
+        std::fs::write(&b, "[[app]]\nname = \"two\"\npath = \"/bin/true\"\n").unwrap();
+
+        let merged = load_configs(&[a.clone(), b.clone()]).unwrap();
+        assert_eq!(merged.interval, Some(2));
+        assert_eq!(merged.apps.len(), 2);
+
+        // A duplicate `name` across files is an error.
+        std::fs::write(&b, "[[app]]\nname = \"one\"\npath = \"/bin/true\"\n").unwrap();
+        assert!(load_configs(&[a.clone(), b.clone()]).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rotating_sink_shifts_rotated_files() {
+        let dir = std::env::temp_dir().join("spawner_rotate_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("out.log");
+        let cfg = LogConfig {
+            max_size_bytes: 4,
+            keep: 2,
+            compress: Compress::None,
+        };
+        let mut sink = RotatingSink::new(base.clone(), &cfg).unwrap();
+
+        // Each write exceeds max_size, forcing a rotation before it lands.
+        sink.write_all(b"aaaaa").unwrap();
+        sink.write_all(b"bbbbb").unwrap();
+        sink.write_all(b"ccccc").unwrap();
+        sink.flush().unwrap();
+
+        // Newest rotated content is in .1, the previous one shifted to .2.
+        assert_eq!(std::fs::read(sink.rotated(1)).unwrap(), b"bbbbb");
+        assert_eq!(std::fs::read(sink.rotated(2)).unwrap(), b"aaaaa");
+        assert_eq!(std::fs::read(&base).unwrap(), b"ccccc");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn restart_policy_should_restart() {
+        assert!(!RestartPolicy::No.should_restart(Some(1), None));
+        assert!(RestartPolicy::OnFailure.should_restart(Some(1), None));
+        assert!(!RestartPolicy::OnFailure.should_restart(Some(0), None));
+        assert!(RestartPolicy::OnFailure.should_restart(None, Some(9)));
+        assert!(RestartPolicy::Always.should_restart(Some(0), None));
+    }
 }